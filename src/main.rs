@@ -20,14 +20,54 @@ pub struct File {
 
 mod rensa;
 
+/// Checks `minhasher` against `lsh` and, if nothing already in the index is
+/// similar enough, claims the next id from `accepted` and inserts it.
+/// Pulled out of the phase-two loop so the "first seen wins" decision --
+/// the one thing that must stay deterministic under the two-phase
+/// parallel-hash/serial-insert pipeline -- is independently testable.
+///
+/// # Returns
+///
+/// The claimed key if `minhasher` was accepted and inserted, or `None` if it
+/// matched an existing document and was left out.
+fn accept_and_insert(
+    lsh: &mut rensa::RMinHashLSH,
+    accepted: &AtomicU64,
+    minhasher: &rensa::RMinHash,
+) -> Option<usize> {
+    match lsh.any_matches(minhasher) {
+        Some(_) => None,
+        None => {
+            let key = accepted.fetch_add(1, SeqCst) as usize;
+            lsh.insert(key, minhasher);
+            Some(key)
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("Gotta give me one arg, a filename!! you gave {:?}", args);
-        return;
+    let mut filename: Option<String> = None;
+    let mut index_path: Option<String> = None;
+    let mut arg_idx = 1;
+    while arg_idx < args.len() {
+        match args[arg_idx].as_str() {
+            "--index" => {
+                arg_idx += 1;
+                index_path = args.get(arg_idx).cloned();
+            }
+            other => filename = Some(other.to_string()),
+        }
+        arg_idx += 1;
     }
-    let filename = args[1].clone();
+    let filename = match filename {
+        Some(filename) => filename,
+        None => {
+            println!("Gotta give me one arg, a filename!! you gave {:?}", args);
+            return;
+        }
+    };
     println!("Operating with filename {}", filename);
     let file = std::fs::File::open(filename).unwrap();
     let progress_bar = ProgressBar::new(file.metadata().unwrap().len());
@@ -38,16 +78,24 @@ async fn main() {
     let buf_reader = BufReader::with_capacity(128 * 1024, file);
 
     // copied params from The Stack
-    let big_lsh = Arc::new(parking_lot::RwLock::new(rensa::RMinHashLSH::new(
-        0.7, 256, 5,
-    )));
+    let big_lsh = Arc::new(parking_lot::RwLock::new(match &index_path {
+        Some(path) if std::path::Path::new(path).exists() => {
+            println!("Loading existing index from {}", path);
+            rensa::RMinHashLSH::load(path).expect("failed to load --index path")
+        }
+        _ => rensa::RMinHashLSH::new(0.7, 256, 5, None),
+    }));
 
     let accepted_tokens = Arc::new(AtomicU64::new(0));
     let accepted_bytes = Arc::new(AtomicU64::new(0));
 
     let enc = cl100k_base().unwrap();
 
-    let accepted = Arc::new(AtomicU64::new(0));
+    // Seed the id counter from the loaded index's next free key instead of
+    // always starting at 0 -- otherwise inserts against a reloaded index
+    // reuse keys `0..M` and silently overwrite the signatures/band entries
+    // of the documents already in it.
+    let accepted = Arc::new(AtomicU64::new(big_lsh.read().next_key() as u64));
     let content_bytes = Arc::new(AtomicU64::new(0));
 
     let out_file = Arc::new(parking_lot::Mutex::new(BufWriter::with_capacity(
@@ -55,61 +103,123 @@ async fn main() {
         std::fs::File::create("fuzzy_deduped_full_out.jsonl").unwrap(),
     )));
 
-    buf_reader.lines()
-            .enumerate()
-            .par_bridge()
-            .for_each(|(line_idx, line_result)| {
-                let line = match line_result {
-                    Ok(val) => val,
-                    Err(e) => {
-                        eprintln!("Got error decoding line: {}", e);
-                        return;
-                    }
-                };
-                if line_idx % 10000 == 0 {
-                    progress_bar.set_message(format!(
-                        "At line {}, {} were accepted. {:.2}B accepted tok / {:.2}GB accepted byte ({:.2}GB total byte)",
-                        line_idx,
-                        accepted.load(SeqCst),
-                        (accepted_tokens.load(SeqCst) as f64) / (1_000_000_000 as f64),
-                        (accepted_bytes.load(SeqCst) as f64) / ((1024 * 1024 * 1024) as f64),
-                        (content_bytes.load(SeqCst) as f64) / ((1024 * 1024 * 1024) as f64),
-                    ));
+    // Lines are processed in fixed-size batches rather than all at once:
+    // phase one (below) computes a batch's MinHash signatures in parallel
+    // with no lock at all, then phase two streams just that batch's
+    // signatures into the LSH index in input order. This keeps the
+    // lock-contention fix (the expensive tokenize-and-hash work no longer
+    // queues behind big_lsh's write lock) without holding the whole corpus
+    // -- or a full copy of every File's contents -- in memory at once.
+    const BATCH_LINES: usize = 8192;
+
+    let mut lines_iter = buf_reader.lines();
+    let mut line_idx: usize = 0;
+    loop {
+        let batch: Vec<String> = lines_iter
+            .by_ref()
+            .take(BATCH_LINES)
+            .filter_map(|line_result| match line_result {
+                Ok(val) => Some(val),
+                Err(e) => {
+                    eprintln!("Got error decoding line: {}", e);
+                    None
                 }
+            })
+            .collect();
+        if batch.is_empty() {
+            break;
+        }
+
+        // Phase one: hash every file in this batch in parallel, no lock.
+        let signatures: Vec<Vec<(File, rensa::RMinHash)>> = batch
+            .par_iter()
+            .map(|line| {
                 progress_bar.inc(line.len() as u64 + 1); // 1 for newline
 
-                for file_with_contents in Deserializer::from_str(&line).into_iter() {
-                    let file_with_contents: File = match file_with_contents {
-                        Ok(val) => val,
-                        Err(e) => {
+                Deserializer::from_str(line)
+                    .into_iter::<File>()
+                    .filter_map(|file_with_contents| match file_with_contents {
+                        Ok(val) => Some(val),
+                        Err(_e) => {
                             println!("error!");
-                            continue;
+                            None
                         }
-                    };
-                    content_bytes.fetch_add(file_with_contents.contents.len() as u64, SeqCst);
-                    let contents = file_with_contents.contents.to_lowercase();
+                    })
+                    .map(|file_with_contents| {
+                        content_bytes.fetch_add(file_with_contents.contents.len() as u64, SeqCst);
+                        let contents = file_with_contents.contents.to_lowercase();
 
-                    let mut minhasher = rensa::RMinHash::new(256, 0);
-                    minhasher.update(contents.split(' ').collect());
+                        let mut minhasher = rensa::RMinHash::new(256, 0);
+                        minhasher.update(contents.split(' ').collect());
 
-                    let mut lsh = big_lsh.write();
-                    match lsh.any_matches(&minhasher) {
-                        Some(_matches) => {
-                            // if it matches previous code, throw it away
-                        }
-                        None => {
-                            // if it matches nothing, accept it as data
-                            let current_accepted = accepted.fetch_add(1, SeqCst);
-                            lsh.insert(current_accepted as usize, &minhasher);
-                            drop(lsh); // Release the write lock
-
-                            accepted_bytes.fetch_add(file_with_contents.contents.len() as u64, SeqCst);
-                            accepted_tokens.fetch_add(enc.encode_ordinary(&file_with_contents.contents).len() as u64, SeqCst);
-                            if let Ok(serialized) = serde_json::to_string(&file_with_contents) {
-                                writeln!(out_file.lock(), "{}", serialized).unwrap();
-                            }
-                        }
+                        (file_with_contents, minhasher)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Phase two: stream this batch's signatures into the LSH index in
+        // input order (one thread, no lock contention within the batch) so
+        // "first seen wins" deterministically decides which duplicate
+        // survives.
+        let mut lsh = big_lsh.write();
+        for per_line_signatures in signatures {
+            if line_idx % 10000 == 0 {
+                progress_bar.set_message(format!(
+                    "At line {}, {} were accepted. {:.2}B accepted tok / {:.2}GB accepted byte ({:.2}GB total byte)",
+                    line_idx,
+                    accepted.load(SeqCst),
+                    (accepted_tokens.load(SeqCst) as f64) / (1_000_000_000 as f64),
+                    (accepted_bytes.load(SeqCst) as f64) / ((1024 * 1024 * 1024) as f64),
+                    (content_bytes.load(SeqCst) as f64) / ((1024 * 1024 * 1024) as f64),
+                ));
+            }
+            line_idx += 1;
+
+            for (file_with_contents, minhasher) in per_line_signatures {
+                if accept_and_insert(&mut lsh, &accepted, &minhasher).is_some() {
+                    accepted_bytes.fetch_add(file_with_contents.contents.len() as u64, SeqCst);
+                    accepted_tokens.fetch_add(enc.encode_ordinary(&file_with_contents.contents).len() as u64, SeqCst);
+                    if let Ok(serialized) = serde_json::to_string(&file_with_contents) {
+                        writeln!(out_file.lock(), "{}", serialized).unwrap();
                     }
                 }
-            });
+                // else: verified via true Jaccard, not just a band collision -- throw it away
+            }
+        }
+        drop(lsh);
+    }
+
+    if let Some(path) = index_path {
+        println!("Flushing updated index to {}", path);
+        big_lsh.read().save(path).expect("failed to save --index path");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_and_insert_keeps_first_seen_on_duplicates() {
+        let mut lsh = rensa::RMinHashLSH::new(0.8, 128, 16, None);
+        let accepted = AtomicU64::new(0);
+
+        let mut first = rensa::RMinHash::new(128, 0);
+        first.update(vec!["the", "quick", "brown", "fox"]);
+        let first_key = accept_and_insert(&mut lsh, &accepted, &first)
+            .expect("first document has nothing to collide with");
+
+        let mut duplicate = rensa::RMinHash::new(128, 0);
+        duplicate.update(vec!["the", "quick", "brown", "fox"]);
+        assert_eq!(accept_and_insert(&mut lsh, &accepted, &duplicate), None);
+
+        let mut distinct = rensa::RMinHash::new(128, 0);
+        distinct.update(vec!["completely", "different", "words", "here"]);
+        let distinct_key = accept_and_insert(&mut lsh, &accepted, &distinct)
+            .expect("unrelated document should still be accepted");
+
+        assert_ne!(first_key, distinct_key);
+        assert_eq!(accepted.load(SeqCst), 2);
+    }
 }
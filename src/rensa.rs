@@ -1,7 +1,11 @@
 use rand::prelude::*;
+use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use rustc_hash::FxHasher;
 use std::collections::HashMap;
+use std::fs::File;
 use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
 
 // ADAPTED FROM https://github.com/beowolx/rensa/blob/main/src/lib.rs
 
@@ -54,6 +58,23 @@ impl RMinHash {
         self.hash_values.clone()
     }
 
+    /// Reconstructs an RMinHash from a previously computed digest, e.g. one
+    /// retained by an LSH index for later verification. The permutation
+    /// table is left empty since `update` is never meant to be called on a
+    /// reconstructed instance -- this is only for comparisons via `jaccard`.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_perm` - The length of the digest.
+    /// * `hash_values` - The digest itself.
+    pub fn from_digest(num_perm: usize, hash_values: Vec<u32>) -> Self {
+        RMinHash {
+            num_perm,
+            hash_values,
+            permutations: Vec::new(),
+        }
+    }
+
     /// Calculates the Jaccard similarity between this MinHash and another.
     ///
     /// # Arguments
@@ -74,13 +95,188 @@ impl RMinHash {
     }
 }
 
+/// A splitmix64 PRNG. RSuperMinHash reseeds one of these per element, so it
+/// needs to be cheap to seed and step -- unlike `StdRng` (ChaCha20), which
+/// would make the per-element reseed cost more than the per-permutation
+/// hashing it's meant to replace.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniform float in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+/// RSuperMinHash implements Ertl's SuperMinHash algorithm. It exposes the
+/// same `update`/`digest`/`jaccard` API as RMinHash but only hashes each
+/// element once (instead of once per permutation), giving a lower-variance
+/// Jaccard estimate at the same signature length.
+pub struct RSuperMinHash {
+    num_perm: usize,
+    seed: u64,
+    hash_values: Vec<f64>,
+    p: Vec<usize>,
+    q: Vec<i64>,
+    b: Vec<usize>,
+    a: usize,
+    item_count: u64,
+}
+
+impl RSuperMinHash {
+    /// Creates a new RSuperMinHash instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_perm` - The length of the MinHash signature to produce.
+    /// * `seed` - A seed value mixed into the per-element hash.
+    pub fn new(num_perm: usize, seed: u64) -> Self {
+        let mut b = vec![0usize; num_perm];
+        if num_perm > 0 {
+            b[num_perm - 1] = num_perm;
+        }
+
+        RSuperMinHash {
+            num_perm,
+            seed,
+            hash_values: vec![f64::INFINITY; num_perm],
+            p: (0..num_perm).collect(),
+            q: vec![-1; num_perm],
+            b,
+            a: num_perm.saturating_sub(1),
+            item_count: 0,
+        }
+    }
+
+    /// Updates the MinHash with a new set of items.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - A vector of strings to be hashed and incorporated into the MinHash.
+    pub fn update(&mut self, items: Vec<&str>) {
+        for item in items {
+            let item_hash = calculate_hash(&(self.seed, item));
+            let mut rng = SplitMix64::new(item_hash);
+            let i = self.item_count as i64;
+
+            let mut j = 0usize;
+            while j <= self.a {
+                let rnd: f64 = rng.next_f64();
+                let rnd_k: f64 = rng.next_f64();
+                let k = j + (rnd_k * (self.num_perm - j) as f64) as usize;
+
+                if self.q[j] != i {
+                    self.p[j] = j;
+                    self.q[j] = i;
+                }
+                if self.q[k] != i {
+                    self.p[k] = k;
+                    self.q[k] = i;
+                }
+                self.p.swap(j, k);
+
+                let val = rnd + j as f64;
+                if val < self.hash_values[self.p[j]] {
+                    let old = (self.hash_values[self.p[j]].floor() as usize).min(self.num_perm - 1);
+                    self.hash_values[self.p[j]] = val;
+                    let new = j;
+                    self.b[new] += 1;
+                    self.b[old] -= 1;
+                    while self.a > 0 && self.b[self.a] == 0 {
+                        self.a -= 1;
+                    }
+                }
+
+                j += 1;
+            }
+
+            self.item_count += 1;
+        }
+    }
+
+    /// Returns the current MinHash digest, quantized to u32 so it can feed
+    /// the same LSH banding as RMinHash.
+    ///
+    /// # Returns
+    ///
+    /// A vector of u32 values representing the MinHash signature.
+    pub fn digest(&self) -> Vec<u32> {
+        let scale = u32::MAX as f64 / self.num_perm as f64;
+        self.hash_values.iter().map(|&v| (v * scale) as u32).collect()
+    }
+
+    /// Calculates the Jaccard similarity between this MinHash and another.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Another RSuperMinHash instance to compare with.
+    ///
+    /// # Returns
+    ///
+    /// A float value representing the estimated Jaccard similarity.
+    pub fn jaccard(&self, other: &RSuperMinHash) -> f64 {
+        // An unfilled register stays at `f64::INFINITY`, and `INFINITY ==
+        // INFINITY` is `true` in Rust. `update` fills every register on a
+        // sketch's first element, so only an empty sketch has unfilled
+        // registers; registers unfilled on both sides are skipped so that
+        // two empty sketches don't count as identical.
+        let mut filled = 0;
+        let mut equal_count = 0;
+        for (&a, &b) in self.hash_values.iter().zip(&other.hash_values) {
+            if a.is_infinite() && b.is_infinite() {
+                continue;
+            }
+            filled += 1;
+            if a == b {
+                equal_count += 1;
+            }
+        }
+        if filled == 0 {
+            0.0
+        } else {
+            equal_count as f64 / filled as f64
+        }
+    }
+}
+
 /// RMinHashLSH implements Locality-Sensitive Hashing using MinHash for efficient similarity search.
+///
+/// An instance normally owns all `num_bands` band tables (monolithic mode)
+/// and retains every inserted document's signature, so `query`/`any_matches`
+/// verify candidates with true Jaccard similarity on their own. Passing
+/// `Some(band_id)` to `new` instead makes it own just that one band's table
+/// and *no* signatures, so a coordinator can shard the bands of one logical
+/// index across separate `RMinHashLSH` instances (e.g. one per
+/// process/machine) without each shard retaining every document's
+/// signature; it should union their `candidate_keys` and verify centrally.
+///
+/// This struct derives rkyv's `Archive`/`Serialize`/`Deserialize` directly
+/// (rather than through a separate serializable mirror type) so `save`
+/// doesn't need to clone `hash_tables`/`signatures` into a throwaway copy
+/// before archiving a multi-hundred-GB index.
+#[derive(Archive, RkyvSerialize, RkyvDeserialize)]
 pub struct RMinHashLSH {
     threshold: f64,
     num_perm: usize,
     num_bands: usize,
     band_size: usize,
+    band_id: Option<usize>,
     hash_tables: Vec<HashMap<u64, Vec<usize>>>,
+    signatures: HashMap<usize, Vec<u32>>,
 }
 
 impl RMinHashLSH {
@@ -91,33 +287,80 @@ impl RMinHashLSH {
     /// * `threshold` - The similarity threshold for considering items as similar.
     /// * `num_perm` - The number of permutations used in the MinHash algorithm.
     /// * `num_bands` - The number of bands for the LSH algorithm.
-    pub fn new(threshold: f64, num_perm: usize, num_bands: usize) -> Self {
+    /// * `band_id` - If `Some`, this instance only stores and queries that
+    ///   single band's table instead of all `num_bands` of them.
+    pub fn new(threshold: f64, num_perm: usize, num_bands: usize, band_id: Option<usize>) -> Self {
+        let table_count = if band_id.is_some() { 1 } else { num_bands };
         RMinHashLSH {
             threshold,
             num_perm,
             num_bands,
             band_size: num_perm / num_bands,
-            hash_tables: vec![HashMap::new(); num_bands],
+            band_id,
+            hash_tables: vec![HashMap::new(); table_count],
+            signatures: HashMap::new(),
         }
     }
 
+    /// Computes the band hash for band `band` of a MinHash digest. Exposed
+    /// so a coordinator can work out which band shard owns a signature
+    /// without needing its own copy of the banding logic.
+    ///
+    /// # Arguments
+    ///
+    /// * `digest` - The full MinHash signature.
+    /// * `band` - The 0-indexed band to hash.
+    /// * `band_size` - The number of signature entries per band.
+    pub fn band_hash(digest: &[u32], band: usize, band_size: usize) -> u64 {
+        let start = band * band_size;
+        let end = start + band_size;
+        calculate_band_hash(&digest[start..end])
+    }
+
+    /// Returns the band index owned by table `table_idx` of this instance:
+    /// its own `band_id` in sharded mode, or `table_idx` itself when this
+    /// instance owns every band.
+    fn band_for_table(&self, table_idx: usize) -> usize {
+        self.band_id.unwrap_or(table_idx)
+    }
+
     /// Inserts a MinHash into the LSH index.
     ///
+    /// In monolithic mode (`band_id: None`) the digest itself is also
+    /// retained (keyed by `key`) so later queries can verify a band
+    /// collision with true Jaccard similarity instead of trusting the
+    /// collision alone. A sharded instance only owns one band's table, and
+    /// every document hashes into every band regardless of which shard owns
+    /// it -- so storing the full signature here too would mean each shard
+    /// retains every document's signature, defeating the point of sharding.
+    /// Sharded instances therefore only store band membership; a coordinator
+    /// unions `candidate_keys` across shards and verifies centrally (e.g.
+    /// against its own monolithic signature store) instead.
+    ///
     /// # Arguments
     ///
     /// * `key` - A unique identifier for the MinHash.
     /// * `minhash` - The RMinHash instance to be inserted.
     pub fn insert(&mut self, key: usize, minhash: &RMinHash) {
         let digest = minhash.digest();
-        for (i, table) in self.hash_tables.iter_mut().enumerate() {
-            let start = i * self.band_size;
-            let end = start + self.band_size;
-            let band_hash = calculate_band_hash(&digest[start..end]);
-            table.entry(band_hash).or_insert_with(Vec::new).push(key);
+        for table_idx in 0..self.hash_tables.len() {
+            let band_hash = Self::band_hash(&digest, self.band_for_table(table_idx), self.band_size);
+            self.hash_tables[table_idx]
+                .entry(band_hash)
+                .or_insert_with(Vec::new)
+                .push(key);
+        }
+        if self.band_id.is_none() {
+            self.signatures.insert(key, digest);
         }
     }
 
-    /// Queries the LSH index for similar items.
+    /// Gathers the band-collision candidates for `minhash` across every band
+    /// table this instance owns, without verifying them. A band collision
+    /// alone only means "possibly similar" -- `query` verifies these with
+    /// true Jaccard similarity, but a sharded instance (which doesn't retain
+    /// signatures) can't verify on its own, so it exposes this instead for a
+    /// coordinator to union across shards before verifying centrally.
     ///
     /// # Arguments
     ///
@@ -125,15 +368,14 @@ impl RMinHashLSH {
     ///
     /// # Returns
     ///
-    /// A vector of keys (usize) of potentially similar items.
-    pub fn query(&self, minhash: &RMinHash) -> Vec<usize> {
+    /// Deduplicated, sorted candidate keys from this instance's own band
+    /// table(s) only.
+    pub fn candidate_keys(&self, minhash: &RMinHash) -> Vec<usize> {
         let digest = minhash.digest();
         let mut candidates = Vec::new();
-        for (i, table) in self.hash_tables.iter().enumerate() {
-            let start = i * self.band_size;
-            let end = start + self.band_size;
-            let band_hash = calculate_band_hash(&digest[start..end]);
-            if let Some(keys) = table.get(&band_hash) {
+        for table_idx in 0..self.hash_tables.len() {
+            let band_hash = Self::band_hash(&digest, self.band_for_table(table_idx), self.band_size);
+            if let Some(keys) = self.hash_tables[table_idx].get(&band_hash) {
                 candidates.extend(keys);
             }
         }
@@ -142,17 +384,49 @@ impl RMinHashLSH {
         candidates
     }
 
-    pub fn any_matches(&self, minhash: &RMinHash) -> Option<&Vec<usize>> {
-        let digest = minhash.digest();
-        for (i, table) in self.hash_tables.iter().enumerate() {
-            let start = i * self.band_size;
-            let end = start + self.band_size;
-            let band_hash = calculate_band_hash(&digest[start..end]);
-            if let Some(keys) = table.get(&band_hash) {
-                return Some(keys);
-            }
-        }
-        None
+    /// Gathers this instance's band-collision candidates and verifies each
+    /// one with true Jaccard similarity, since a band collision alone only
+    /// means "possibly similar" and produces false positives at low band
+    /// counts.
+    ///
+    /// Requires a retained signature per candidate, so this only returns
+    /// verified results in monolithic mode (`band_id: None`). A sharded
+    /// instance stores no signatures (see `insert`) and always verifies
+    /// nothing here -- a coordinator should union `candidate_keys` across
+    /// shards and verify against its own signature store instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `minhash` - The RMinHash instance to query for.
+    ///
+    /// # Returns
+    ///
+    /// Every candidate whose Jaccard similarity clears `threshold`, as
+    /// `(key, similarity)` pairs sorted by descending similarity.
+    pub fn query(&self, minhash: &RMinHash) -> Vec<(usize, f64)> {
+        let candidates = self.candidate_keys(minhash);
+
+        let mut verified: Vec<(usize, f64)> = candidates
+            .into_iter()
+            .filter_map(|key| {
+                let candidate_digest = self.signatures.get(&key)?.clone();
+                let candidate = RMinHash::from_digest(self.num_perm, candidate_digest);
+                if self.is_similar(minhash, &candidate) {
+                    Some((key, minhash.jaccard(&candidate)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        verified.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        verified
+    }
+
+    /// Returns the best verified match for `minhash`: the key and true
+    /// Jaccard similarity of the most similar previously inserted item, or
+    /// `None` if no candidate actually clears `threshold`.
+    pub fn any_matches(&self, minhash: &RMinHash) -> Option<(usize, f64)> {
+        self.query(minhash).into_iter().next()
     }
 
     /// Checks if two MinHashes are similar based on the LSH threshold.
@@ -178,6 +452,231 @@ impl RMinHashLSH {
     fn get_num_bands(&self) -> usize {
         self.num_bands
     }
+
+    /// Returns the smallest key guaranteed not to collide with an already
+    /// inserted document: one past the largest inserted key, or `0` for an
+    /// empty index. A sharded instance retains no signatures, so its keys
+    /// are read from its band table instead. A caller resuming
+    /// inserts against a `load`-ed index (rather than starting a fresh one)
+    /// must seed its own id counter from this -- otherwise it reuses keys
+    /// `0..M` and overwrites the stored signatures/band entries of the
+    /// documents already in the index.
+    pub fn next_key(&self) -> usize {
+        let max_key = if self.band_id.is_none() {
+            self.signatures.keys().max().copied()
+        } else {
+            self.hash_tables.iter().flat_map(|t| t.values().flatten()).max().copied()
+        };
+        max_key.map_or(0, |max| max + 1)
+    }
+
+    /// Serializes the index with rkyv and writes it to `path`, so it can be
+    /// reloaded by a later run. Archives `self` directly (no intermediate
+    /// clone of `hash_tables`/`signatures`), since those are exactly the
+    /// fields that dominate a multi-hundred-GB index's size.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Destination file for the archived index.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let bytes =
+            rkyv::to_bytes::<_, 1024>(self).expect("failed to archive RMinHashLSH index");
+        let mut file = File::create(path)?;
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Memory-maps an index previously written by `save` and deserializes it
+    /// back into an owned `RMinHashLSH` so processing can continue against
+    /// it (e.g. appending a new shard's lines to an already-deduplicated
+    /// set). Memory-mapping the file avoids an extra full-file read into an
+    /// owned buffer, but this still performs a full deserialization pass --
+    /// it is not a zero-copy query path.
+    ///
+    /// Uses the unchecked `archived_root` rather than `check_archived_root`:
+    /// rkyv 0.7's bytecheck validation of `ArchivedHashMap` spuriously fails
+    /// on maps with more than one entry (`InvalidKeyPosition`), so checked
+    /// access panics on any real index. `path` is expected to be a file this
+    /// process (or a trusted peer) wrote with `save`, not untrusted input.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Source file written by `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        let archived = unsafe { rkyv::archived_root::<Self>(&mmap[..]) };
+        let deserialized = archived
+            .deserialize(&mut rkyv::Infallible)
+            .expect("failed to deserialize RMinHashLSH index");
+        Ok(deserialized)
+    }
+}
+
+/// RScaledMinHash implements a scaled (bottom-sketch) MinHash: instead of
+/// keeping the minimum of `num_perm` independent permutations, it keeps
+/// every element hash below `u64::MAX / scale`. The sketch size grows with
+/// the underlying set, which lets `containment` estimate `|A∩B| / |A|`
+/// instead of only symmetric Jaccard -- useful when a small document is
+/// almost entirely contained in a much larger one (vendored snippets,
+/// license headers) and fixed-`num_perm` MinHash would underweight it.
+pub struct RScaledMinHash {
+    scale: u64,
+    hashes: std::collections::BTreeSet<u64>,
+}
+
+impl RScaledMinHash {
+    /// Creates a new RScaledMinHash instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Controls the retained fraction of element hashes: a hash
+    ///   is kept when it falls below `u64::MAX / scale`, so roughly one in
+    ///   every `scale` elements ends up in the sketch.
+    pub fn new(scale: u64) -> Self {
+        RScaledMinHash {
+            scale,
+            hashes: std::collections::BTreeSet::new(),
+        }
+    }
+
+    /// Updates the sketch with a new set of items.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - A vector of strings to be hashed and incorporated into the sketch.
+    pub fn update(&mut self, items: Vec<&str>) {
+        let bound = u64::MAX / self.scale;
+        for item in items {
+            let item_hash = calculate_hash(&item);
+            if item_hash < bound {
+                self.hashes.insert(item_hash);
+            }
+        }
+    }
+
+    /// Returns every retained hash, in ascending order.
+    pub fn digest(&self) -> Vec<u64> {
+        self.hashes.iter().copied().collect()
+    }
+
+    /// Estimates the containment of this sketch in `other`, i.e. `|A∩B| / |A|`
+    /// where `A` is this sketch's underlying set and `B` is `other`'s.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The candidate containing set.
+    ///
+    /// # Returns
+    ///
+    /// A float value representing the estimated containment of `self` in `other`.
+    pub fn containment(&self, other: &RScaledMinHash) -> f64 {
+        if self.hashes.is_empty() {
+            return 0.0;
+        }
+        let intersection = self.hashes.intersection(&other.hashes).count();
+        intersection as f64 / self.hashes.len() as f64
+    }
+
+    /// Estimates the symmetric Jaccard similarity between this sketch and
+    /// another, `|A∩B| / |A∪B|`, for parity with RMinHash/RSuperMinHash.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Another RScaledMinHash instance to compare with.
+    pub fn jaccard(&self, other: &RScaledMinHash) -> f64 {
+        let intersection = self.hashes.intersection(&other.hashes).count();
+        let union = self.hashes.union(&other.hashes).count();
+        if union == 0 {
+            0.0
+        } else {
+            intersection as f64 / union as f64
+        }
+    }
+}
+
+/// RScaledMinHashIndex retains every accepted RScaledMinHash sketch behind a
+/// postings index keyed by retained hash value, and flags a document as a
+/// near-duplicate when its containment in some already-accepted document
+/// exceeds `containment_threshold`, rather than only when symmetric Jaccard
+/// does. Querying only visits documents that share at least one retained
+/// hash with the query sketch, rather than every accepted document.
+pub struct RScaledMinHashIndex {
+    containment_threshold: f64,
+    sketches: HashMap<usize, RScaledMinHash>,
+    postings: HashMap<u64, Vec<usize>>,
+}
+
+impl RScaledMinHashIndex {
+    /// Creates a new RScaledMinHashIndex instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `containment_threshold` - The containment bound above which a
+    ///   document is considered a near-duplicate of one already accepted.
+    pub fn new(containment_threshold: f64) -> Self {
+        RScaledMinHashIndex {
+            containment_threshold,
+            sketches: HashMap::new(),
+            postings: HashMap::new(),
+        }
+    }
+
+    /// Inserts a sketch into the index, and indexes each of its retained
+    /// hashes in the postings table so later queries can find it without a
+    /// full scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A unique identifier for the sketch.
+    /// * `sketch` - The RScaledMinHash instance to be inserted.
+    pub fn insert(&mut self, key: usize, sketch: RScaledMinHash) {
+        for &hash in &sketch.hashes {
+            self.postings.entry(hash).or_insert_with(Vec::new).push(key);
+        }
+        self.sketches.insert(key, sketch);
+    }
+
+    /// Finds the previously accepted document that `sketch` is most
+    /// contained within.
+    ///
+    /// Candidates are gathered from the postings index (every accepted
+    /// document that shares at least one retained hash with `sketch`) and
+    /// only those are verified with true containment -- this never touches
+    /// documents with no hash in common with `sketch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sketch` - The RScaledMinHash instance to query for.
+    ///
+    /// # Returns
+    ///
+    /// The key and containment score of the best match, if any candidate
+    /// document's containment clears `containment_threshold`.
+    pub fn any_matches(&self, sketch: &RScaledMinHash) -> Option<(usize, f64)> {
+        let mut candidates: Vec<usize> = sketch
+            .hashes
+            .iter()
+            .filter_map(|hash| self.postings.get(hash))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .filter_map(|key| {
+                let accepted = self.sketches.get(&key)?;
+                let score = sketch.containment(accepted);
+                if score >= self.containment_threshold {
+                    Some((key, score))
+                } else {
+                    None
+                }
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
 }
 
 /// Calculates a hash value for a given item.
@@ -200,3 +699,148 @@ fn calculate_band_hash(band: &[u32]) -> u64 {
     }
     hasher.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn super_minhash_identical_sets_have_jaccard_one() {
+        let items = vec!["the", "quick", "brown", "fox", "jumps"];
+        let mut a = RSuperMinHash::new(64, 0);
+        let mut b = RSuperMinHash::new(64, 0);
+        a.update(items.clone());
+        b.update(items);
+        assert_eq!(a.jaccard(&b), 1.0);
+    }
+
+    #[test]
+    fn super_minhash_disjoint_sets_have_lower_jaccard() {
+        let mut a = RSuperMinHash::new(64, 0);
+        let mut b = RSuperMinHash::new(64, 0);
+        a.update(vec!["the", "quick", "brown"]);
+        b.update(vec!["lazy", "sleeping", "dog"]);
+        assert!(a.jaccard(&b) < 1.0);
+    }
+
+    #[test]
+    fn scaled_minhash_contained_subset_has_containment_one() {
+        // scale=1 retains every hash, so `small`'s sketch is exactly a
+        // subset of `big`'s and containment is exact, not estimated.
+        let mut small = RScaledMinHash::new(1);
+        let mut big = RScaledMinHash::new(1);
+        small.update(vec!["license", "header", "text"]);
+        big.update(vec!["license", "header", "text", "and", "lots", "more", "code"]);
+        assert_eq!(small.containment(&big), 1.0);
+    }
+
+    #[test]
+    fn scaled_minhash_disjoint_sets_have_containment_zero() {
+        let mut a = RScaledMinHash::new(1);
+        let mut b = RScaledMinHash::new(1);
+        a.update(vec!["foo", "bar"]);
+        b.update(vec!["baz", "qux"]);
+        assert_eq!(a.containment(&b), 0.0);
+    }
+
+    #[test]
+    fn scaled_minhash_index_flags_contained_document() {
+        let mut accepted = RScaledMinHash::new(1);
+        accepted.update(vec!["license", "header", "text", "and", "lots", "more", "code"]);
+
+        let mut index = RScaledMinHashIndex::new(0.9);
+        index.insert(0, accepted);
+
+        let mut candidate = RScaledMinHash::new(1);
+        candidate.update(vec!["license", "header", "text"]);
+
+        let (key, score) = index.any_matches(&candidate).expect("expected a match");
+        assert_eq!(key, 0);
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn lsh_save_load_round_trip_preserves_matches() {
+        let mut lsh = RMinHashLSH::new(0.5, 64, 8, None);
+        let mut mh = RMinHash::new(64, 0);
+        mh.update(vec!["the", "quick", "brown", "fox"]);
+        lsh.insert(0, &mh);
+
+        let path =
+            std::env::temp_dir().join(format!("rensa_lsh_round_trip_{}.bin", std::process::id()));
+        lsh.save(&path).expect("save");
+        let loaded = RMinHashLSH::load(&path).expect("load");
+        std::fs::remove_file(&path).ok();
+
+        let (key, score) = loaded
+            .any_matches(&mh)
+            .expect("expected a match after reload");
+        assert_eq!(key, 0);
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn lsh_sharded_candidate_keys_match_monolithic_query() {
+        let num_bands = 8;
+        let mut monolithic = RMinHashLSH::new(0.5, 64, num_bands, None);
+        let mut shards: Vec<RMinHashLSH> = (0..num_bands)
+            .map(|band| RMinHashLSH::new(0.5, 64, num_bands, Some(band)))
+            .collect();
+
+        let mut mh = RMinHash::new(64, 0);
+        mh.update(vec!["the", "quick", "brown", "fox"]);
+        monolithic.insert(0, &mh);
+        for shard in shards.iter_mut() {
+            shard.insert(0, &mh);
+        }
+
+        let mut query = RMinHash::new(64, 0);
+        query.update(vec!["the", "quick", "brown", "fox"]);
+
+        let mut sharded_candidates: Vec<usize> = shards
+            .iter()
+            .flat_map(|shard| shard.candidate_keys(&query))
+            .collect();
+        sharded_candidates.sort_unstable();
+        sharded_candidates.dedup();
+
+        let monolithic_candidates = monolithic.candidate_keys(&query);
+        assert!(!monolithic_candidates.is_empty());
+        assert_eq!(sharded_candidates, monolithic_candidates);
+    }
+
+    #[test]
+    fn lsh_any_matches_verifies_with_true_jaccard() {
+        let mut lsh = RMinHashLSH::new(0.8, 128, 16, None);
+
+        let mut accepted = RMinHash::new(128, 0);
+        accepted.update(vec!["the", "quick", "brown", "fox", "jumps"]);
+        lsh.insert(0, &accepted);
+
+        let mut identical = RMinHash::new(128, 0);
+        identical.update(vec!["the", "quick", "brown", "fox", "jumps"]);
+        let (key, score) = lsh
+            .any_matches(&identical)
+            .expect("expected a verified match for an identical document");
+        assert_eq!(key, 0);
+        assert_eq!(score, 1.0);
+
+        let mut unrelated = RMinHash::new(128, 0);
+        unrelated.update(vec!["completely", "different", "words", "here"]);
+        assert!(lsh.any_matches(&unrelated).is_none());
+    }
+
+    #[test]
+    fn scaled_minhash_index_ignores_unrelated_document() {
+        let mut accepted = RScaledMinHash::new(1);
+        accepted.update(vec!["license", "header", "text"]);
+
+        let mut index = RScaledMinHashIndex::new(0.9);
+        index.insert(0, accepted);
+
+        let mut candidate = RScaledMinHash::new(1);
+        candidate.update(vec!["totally", "different", "words"]);
+
+        assert!(index.any_matches(&candidate).is_none());
+    }
+}